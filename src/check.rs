@@ -0,0 +1,163 @@
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use crate::{CheckError, Trace};
+
+/// Check of a single instance, built by its `CheckType`
+pub trait Check: Send {
+  /// Network checks record their stages in `trace`, shown with `RunOptions::explain`
+  fn check(&self, trace: &mut Trace) -> Result<Outcome, CheckError>;
+}
+
+/// Type of check, constructs checks from configured instances
+pub trait CheckType: Send + Sync {
+  /// Name of the check, also used as the flag that enables it and as `type` in config file
+  fn name(&self) -> &'static str;
+  /// What the check does, shown in help of the flag that enables it
+  fn about(&self) -> &'static str { "" }
+  /// Name of `target` in args and env, e.g. `url` for `--postgres-url` and POSTGRES_URL
+  fn target_name(&self) -> &'static str { "url" }
+  /// What `warning` and `critical` thresholds are compared with
+  fn thresholds(&self) -> &'static str { "response time" }
+  /// Check-specific `options` accepted in config file
+  fn options(&self) -> &'static [CheckOption] { &[] }
+  /// Instance with default target and limits, e.g. `/app/tmp/health.all` with 20s timeout for timestamp
+  fn default_instance(&self) -> Instance {
+    Instance { name: self.name().to_owned(), ..Instance::default() }
+  }
+
+  fn build(&self, instance: Instance) -> Box<dyn Check>;
+  /// Builds one check of several `instances`, or `None` to check each of them on its own.
+  /// E.g. timestamp files with `require = "any"`, of which one fresh file is enough
+  fn build_group(&self, _instances: &[Instance]) -> Option<Box<dyn Check>> { None }
+}
+
+/// Check-specific option, set in `options` table of config file, or with `--<check>-<option>`
+#[derive(Debug, Clone, Copy)]
+pub struct CheckOption {
+  pub name: &'static str,
+  pub help: &'static str,
+  /// Accepted values, any value if empty
+  pub values: &'static [&'static str],
+}
+
+/// Types of checks by name, in order of registration, which is also order of output
+pub struct Registry {
+  types: Vec<Box<dyn CheckType>>,
+}
+
+impl Registry {
+  /// Registry without any types, `Registry::default()` has the built-in ones
+  pub fn new() -> Registry {
+    Registry { types: Vec::new() }
+  }
+
+  /// Registers `check_type` under its name, replacing type registered with the same name
+  pub fn register(&mut self, check_type: impl CheckType + 'static) -> &mut Registry {
+    match self.types.iter_mut().find(|registered| registered.name() == check_type.name()) {
+      Some(registered) => *registered = Box::new(check_type),
+      None => self.types.push(Box::new(check_type)),
+    }
+
+    self
+  }
+
+  pub fn get(&self, name: &str) -> Option<&dyn CheckType> {
+    self.types().find(|check_type| check_type.name() == name)
+  }
+
+  pub fn types(&self) -> impl Iterator<Item = &dyn CheckType> {
+    self.types.iter().map(Box::as_ref)
+  }
+}
+
+impl Default for Registry {
+  /// Registry with built-in types: timestamp, amqp, postgres, redis and http
+  fn default() -> Registry {
+    let mut registry = Registry::new();
+    crate::checks::register(&mut registry);
+
+    registry
+  }
+}
+
+/// Single target of a check, e.g. one of several databases
+#[derive(Debug, Clone, Default)]
+pub struct Instance {
+  pub name: String,
+  /// Url of the server or path to the file. Empty if not configured yet
+  pub target: String,
+  pub timeout: Option<Duration>,
+  /// Thresholds for response time, or for age of the timestamp
+  pub warning: Option<Duration>,
+  pub critical: Option<Duration>,
+  /// Profiles the check belongs to, e.g. `readiness`. Check without profiles belongs to all of them
+  pub profiles: Vec<String>,
+  pub options: BTreeMap<String, String>,
+}
+
+impl Instance {
+  /// Fills target and limits that are not set with ones of `defaults`, e.g. `CheckType::default_instance`
+  pub fn with_defaults(mut self, defaults: &Instance) -> Instance {
+    if self.target.is_empty() { self.target = defaults.target.clone(); }
+    self.timeout = self.timeout.or(defaults.timeout);
+    self.warning = self.warning.or(defaults.warning);
+    self.critical = self.critical.or(defaults.critical);
+
+    self
+  }
+
+  pub fn in_profile(&self, profile: &str) -> bool {
+    self.profiles.is_empty() || self.profiles.iter().any(|p| p == profile)
+  }
+}
+
+/// Successful result of a check
+#[derive(Debug, Clone, Default)]
+pub struct Outcome {
+  /// `Warning` if check passed, but target is degraded, e.g. responds slowly
+  pub severity: Severity,
+  /// Explains why target is degraded
+  pub message: String,
+  /// Performance data, e.g. age of the timestamp
+  pub metrics: Vec<Metric>,
+}
+
+/// Severities in order of importance, as in Nagios plugins
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+  #[default]
+  Ok,
+  Warning,
+  Unknown,
+  Critical,
+}
+
+impl Severity {
+  /// Three-state health, where unknown is counted as unhealthy
+  pub fn health(&self) -> &'static str {
+    match self {
+      Severity::Ok => "healthy",
+      Severity::Warning => "degraded",
+      Severity::Critical | Severity::Unknown => "unhealthy",
+    }
+  }
+
+  pub fn nagios_code(&self) -> i32 {
+    match self {
+      Severity::Ok => 0,
+      Severity::Warning => 1,
+      Severity::Critical => 2,
+      Severity::Unknown => 3,
+    }
+  }
+}
+
+#[derive(Debug, Clone)]
+pub struct Metric {
+  pub name: &'static str,
+  pub value: f64,
+  pub unit: &'static str,
+  pub warning: Option<f64>,
+  pub critical: Option<f64>,
+}
@@ -0,0 +1,73 @@
+use std::time::Instant;
+
+use crate::common::{check_latency, url_host};
+use crate::error::check_error;
+use crate::{Check, CheckError, CheckType, ErrorKind, Instance, Outcome, Trace};
+
+/// Requests URL with GET and expects a successful status
+pub struct HttpType;
+impl CheckType for HttpType {
+  fn name(&self) -> &'static str { "http" }
+
+  fn about(&self) -> &'static str { "Request and await a 200 response" }
+
+  fn default_instance(&self) -> Instance {
+    Instance { name: self.name().to_owned(), target: "http://localhost:8080".to_owned(), ..Instance::default() }
+  }
+
+  fn build(&self, instance: Instance) -> Box<dyn Check> {
+    Box::new(HttpCheck { instance })
+  }
+}
+
+struct HttpCheck {
+  instance: Instance,
+}
+
+impl Check for HttpCheck {
+  fn check(&self, trace: &mut Trace) -> Result<Outcome, CheckError> {
+    let instance = &self.instance;
+    let (url, timeout) = (instance.target.as_str(), instance.timeout);
+    if let Some((host, port)) = url_host(url, 80) { trace.connect(&host, port, timeout)?; }
+    let started = Instant::now();
+
+    let mut agent = ureq::AgentBuilder::new();
+    if let Some(timeout) = timeout {
+      agent = agent.timeout_connect(timeout).timeout(timeout);
+    }
+
+    let result = trace.stage("request", || agent.build().get(url).call().map_err(http_error));
+
+    // ureq does TLS handshake as part of the request, so only its failure can be told apart
+    if let (Err(CheckError { kind: ErrorKind::Tls, .. }), Some(stage)) = (&result, trace.stages.last_mut()) {
+      stage.name = "tls";
+    }
+    result?;
+
+    check_latency(instance, started.elapsed())
+  }
+}
+
+fn http_error(err: ureq::Error) -> CheckError {
+  use ureq::ErrorKind as Http;
+
+  let kind = match err.kind() {
+    Http::HTTP => ErrorKind::Assertion,
+    Http::InvalidUrl | Http::UnknownScheme | Http::InvalidProxyUrl => ErrorKind::Config,
+    Http::Dns => ErrorKind::Dns,
+    Http::ProxyUnauthorized => ErrorKind::Auth,
+    Http::ConnectionFailed | Http::Io if is_tls_error(&err) => ErrorKind::Tls,
+    Http::ConnectionFailed | Http::ProxyConnect => return check_error(&err, ErrorKind::Connect),
+    Http::TooManyRedirects | Http::BadStatus | Http::BadHeader => ErrorKind::Protocol,
+    // `Io`, and kinds added by later ureq 2.x versions
+    _ => return check_error(&err, ErrorKind::Protocol),
+  };
+
+  CheckError::new(kind, err.to_string())
+}
+
+/// TLS errors of rustls are reported by ureq as IO errors with `InvalidData` kind
+fn is_tls_error(err: &ureq::Error) -> bool {
+  let source = std::error::Error::source(err).and_then(|source| source.downcast_ref::<std::io::Error>());
+  matches!(source, Some(source) if source.kind() == std::io::ErrorKind::InvalidData && source.get_ref().is_some())
+}
@@ -0,0 +1,23 @@
+mod amqp;
+mod http;
+mod postgres;
+mod redis;
+mod timestamp;
+
+pub use amqp::AmqpType;
+pub use http::HttpType;
+pub use postgres::PostgresType;
+pub use redis::RedisType;
+pub use timestamp::{write_heartbeat, TimestampType};
+
+use crate::Registry;
+
+/// Registers built-in check types, in order of output
+pub fn register(registry: &mut Registry) {
+  registry
+    .register(TimestampType)
+    .register(AmqpType)
+    .register(PostgresType)
+    .register(RedisType)
+    .register(HttpType);
+}
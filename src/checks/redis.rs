@@ -0,0 +1,73 @@
+use std::time::Instant;
+
+use crate::common::{check_latency, with_timeout};
+use crate::error::is_dns_error;
+use crate::{Check, CheckError, CheckType, ErrorKind, Instance, Outcome, Trace};
+
+/// Connects to Redis server and runs `INFO server`
+pub struct RedisType;
+impl CheckType for RedisType {
+  fn name(&self) -> &'static str { "redis" }
+
+  fn about(&self) -> &'static str { "Connect to server and run INFO server" }
+
+  fn default_instance(&self) -> Instance {
+    Instance { name: self.name().to_owned(), target: "redis://redis:6379/0".to_owned(), ..Instance::default() }
+  }
+
+  fn build(&self, instance: Instance) -> Box<dyn Check> {
+    Box::new(RedisCheck { instance })
+  }
+}
+
+struct RedisCheck {
+  instance: Instance,
+}
+
+impl Check for RedisCheck {
+  fn check(&self, trace: &mut Trace) -> Result<Outcome, CheckError> {
+    let instance = &self.instance;
+    let (url, timeout) = (instance.target.as_str(), instance.timeout);
+
+    let client = redis::Client::open(url).map_err(redis_error)?;
+    if let redis::ConnectionAddr::Tcp(host, port) = &*client.get_connection_info().addr {
+      trace.connect(host, *port, timeout)?;
+    }
+    let started = Instant::now();
+
+    // Connection timeout only covers TCP connect, AUTH and SELECT can still hang
+    let mut con = trace.stage("auth", || {
+      let con = with_timeout(timeout, move || match timeout {
+        Some(timeout) => client.get_connection_with_timeout(timeout),
+        None => client.get_connection(),
+      });
+
+      con.ok_or_else(|| CheckError::new(ErrorKind::Timeout, "Connection timed out"))?.map_err(redis_error)
+    })?;
+    con.set_read_timeout(timeout).map_err(redis_error)?;
+    con.set_write_timeout(timeout).map_err(redis_error)?;
+
+    trace.stage("probe", || {
+      redis::cmd("INFO").arg("server").query::<String>(&mut con).map_err(redis_error)
+    })?;
+
+    check_latency(instance, started.elapsed())
+  }
+}
+
+fn redis_error(err: redis::RedisError) -> CheckError {
+  let message = err.to_string();
+  let kind = match err.kind() {
+    _ if err.is_timeout() => ErrorKind::Timeout,
+    _ if err.is_connection_refusal() => ErrorKind::Connect,
+    // `RedisError` doesn't expose its IO error as `source`
+    redis::ErrorKind::IoError if is_dns_error(&message) => ErrorKind::Dns,
+    redis::ErrorKind::IoError => ErrorKind::Connect,
+    redis::ErrorKind::InvalidClientConfig => ErrorKind::Config,
+    redis::ErrorKind::AuthenticationFailed => ErrorKind::Auth,
+    _ if matches!(err.code(), Some("NOAUTH") | Some("WRONGPASS")) => ErrorKind::Auth,
+    _ => ErrorKind::Protocol,
+  };
+
+  CheckError::new(kind, message)
+}
@@ -0,0 +1,468 @@
+use std::fmt::Write;
+use std::path::Path;
+use std::time::Duration;
+
+use chrono::{DateTime, NaiveDateTime, SecondsFormat, TimeZone, Utc};
+
+use crate::common::parse_duration;
+use crate::{Check, CheckError, CheckOption, CheckType, ErrorKind, Instance, Metric, Outcome, Severity, Trace};
+
+/// Checks that file contains a recent timestamp, e.g. written by a background worker
+pub struct TimestampType;
+impl CheckType for TimestampType {
+  fn name(&self) -> &'static str { "timestamp" }
+
+  fn about(&self) -> &'static str {
+    "Check if file specified with `timestamp-file` has timestamp older than `timestamp-timeout` seconds ago. \
+    File should contain one timestamp in `timestamp-format`. Spaces and newlines are trimmed"
+  }
+
+  fn target_name(&self) -> &'static str { "file" }
+
+  fn thresholds(&self) -> &'static str { "age of the timestamp" }
+
+  fn options(&self) -> &'static [CheckOption] {
+    &[
+      CheckOption {
+        name: "format",
+        help: "Sets format of timestamp: `seconds` (can be fractional), `millis`, `micros`, `rfc3339` or chrono strftime pattern \
+        like `%Y-%m-%d %H:%M:%S`. Default: `auto`, detects epoch units by number of digits, or RFC3339",
+        values: &[],
+      },
+      CheckOption {
+        name: "source",
+        help: "Sets where timestamp is taken from: `content` of the file, or its modification time with `mtime`, \
+        e.g. for files updated with `touch`. Default: `content`",
+        values: &["content", "mtime"],
+      },
+      CheckOption {
+        name: "require",
+        help: "Sets whether `all` files of the check have to be fresh, or `any` of them, with stale ones reported as degraded. \
+        Applies across all files and patterns, which are then reported as one check. Default: `all`",
+        values: &["all", "any"],
+      },
+      CheckOption {
+        name: "max-future",
+        help: "Fails if timestamp is further in the future than this, e.g. because of clock skew between containers sharing a volume. \
+        Default: `1m`",
+        values: &[],
+      },
+    ]
+  }
+
+  fn default_instance(&self) -> Instance {
+    let target = "/app/tmp/health.all".to_owned();
+
+    Instance { name: self.name().to_owned(), target, timeout: Some(DEFAULT_TIMEOUT), ..Instance::default() }
+  }
+
+  fn build(&self, instance: Instance) -> Box<dyn Check> {
+    Box::new(TimestampCheck { entries: vec![instance] })
+  }
+
+  fn build_group(&self, instances: &[Instance]) -> Option<Box<dyn Check>> {
+    let require_any = instances.iter().all(|instance| instance.options.get("require").is_some_and(|require| require == "any"));
+
+    if !require_any { return None; }
+
+    Some(Box::new(TimestampCheck { entries: instances.to_vec() }))
+  }
+}
+
+/// Checks files or patterns of `entries` together, there are several only if any of them is enough
+struct TimestampCheck {
+  entries: Vec<Instance>,
+}
+
+/// Maximum age of timestamp if neither `timeout` nor `critical` are set
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(20);
+
+/// Tolerated clock skew between writer of the file and the check
+const DEFAULT_MAX_FUTURE: Duration = Duration::from_secs(60);
+
+/// Where timestamp of a file is taken from, set with `source` option
+enum Source {
+  Content(Format),
+  Mtime,
+}
+
+/// Settings of a file or pattern, from its instance
+struct Entry<'a> {
+  instance: &'a Instance,
+  source: Source,
+  timeout: i64,
+  warning: Option<i64>,
+  max_future: i64,
+}
+
+impl Check for TimestampCheck {
+  fn check(&self, _trace: &mut Trace) -> Result<Outcome, CheckError> {
+    let entries = self.entries.iter().map(Entry::parse).collect::<Result<Vec<_>, _>>()?;
+    let require_any = match self.entries[0].options.get("require").map_or("all", String::as_str) {
+      "all" => false,
+      "any" => true,
+      require => return Err(config_error(format!("Unknown timestamp requirement `{}`, expected all or any", require))),
+    };
+
+    // Files are named in messages only if there can be several of them, and entries only if there are several
+    let (mut stale, mut degraded, mut ages, mut checked) = (Vec::new(), Vec::new(), Vec::new(), 0);
+    for entry in &entries {
+      let label = |file: Option<&str>| {
+        let name = Some(entry.name().to_owned()).filter(|_| entries.len() > 1);
+        let file = file.filter(|_| is_pattern(&entry.instance.target))
+          .map(|file| Path::new(file).file_name().map_or(file.into(), |name| name.to_string_lossy()).into_owned());
+
+        name.into_iter().chain(file).collect::<Vec<_>>().join(": ")
+      };
+
+      let files = match entry.files() {
+        Ok(files) => files,
+        Err(err) if err.kind == ErrorKind::Config => return Err(err),
+        Err(err) => { checked += 1; stale.push((label(None), err)); continue; }
+      };
+      for file in &files {
+        checked += 1;
+        match entry.age(file) {
+          Ok((age, Some(warning))) => { degraded.push((label(Some(file)), warning)); ages.push((age, entry)); }
+          Ok((age, None)) => ages.push((age, entry)),
+          Err(err) => stale.push((label(Some(file)), err)),
+        }
+      }
+    }
+
+    // Age of the file that decides the result: the oldest one, or the freshest one if any of them is enough
+    let age = if require_any { ages.iter().min_by_key(|(age, _)| *age) } else { ages.iter().max_by_key(|(age, _)| *age) };
+    let metrics: Vec<_> = age.into_iter().map(|&(age, entry)| Metric {
+      name: "age", value: age as f64, unit: "s", warning: entry.warning.map(|w| w as f64), critical: Some(entry.timeout as f64),
+    }).collect();
+
+    let labeled = |label: &str, message: String| if label.is_empty() { message } else { format!("{}: {}", label, message) };
+    let stale_files: Vec<_> = stale.iter().map(|(label, err)| labeled(label, err.to_string())).collect();
+    let degraded_files: Vec<_> = degraded.into_iter().map(|(label, message)| labeled(&label, message)).collect();
+
+    if !stale.is_empty() && (!require_any || stale.len() == checked) {
+      Err(CheckError::new(stale[0].1.kind, stale_files.join(", ")))
+    } else if !stale_files.is_empty() || !degraded_files.is_empty() {
+      let message = stale_files.into_iter().chain(degraded_files).collect::<Vec<_>>().join(", ");
+      Ok(Outcome { severity: Severity::Warning, message, metrics })
+    } else {
+      Ok(Outcome { metrics, ..Outcome::default() })
+    }
+  }
+}
+
+impl<'a> Entry<'a> {
+  fn parse(instance: &'a Instance) -> Result<Entry<'a>, CheckError> {
+    let format = instance.options.get("format").map_or(Ok(Format::Auto), |format| Format::parse(format)).map_err(config_error)?;
+    let source = match instance.options.get("source").map_or("content", String::as_str) {
+      "content" => Source::Content(format),
+      "mtime" => Source::Mtime,
+      source => return Err(config_error(format!("Unknown timestamp source `{}`, expected content or mtime", source))),
+    };
+    let max_future = instance.options.get("max-future").map_or(Ok(DEFAULT_MAX_FUTURE), |value| parse_duration(value))
+      .map_err(|e| config_error(format!("Invalid max-future: {}", e)))?;
+
+    Ok(Entry {
+      instance,
+      source,
+      // `critical` is the same as `timeout` for timestamps, it takes precedence if both are set
+      timeout: instance.critical.or(instance.timeout).unwrap_or(DEFAULT_TIMEOUT).as_secs() as i64,
+      warning: instance.warning.map(|warning| warning.as_secs() as i64),
+      max_future: max_future.as_secs() as i64,
+    })
+  }
+
+  /// Name of the instance without type, e.g. `worker` for `timestamp.worker`
+  fn name(&self) -> &str {
+    let name = self.instance.name.as_str();
+    name.split_once('.').map_or(name, |(_, name)| name)
+  }
+
+  /// Files matching target if it's a glob pattern, or the target itself
+  fn files(&self) -> Result<Vec<String>, CheckError> {
+    let target = self.instance.target.as_str();
+    if !is_pattern(target) { return Ok(vec![target.to_owned()]); }
+
+    let paths = glob::glob(target).map_err(|e| config_error(format!("Invalid pattern: {}", e)))?;
+    let files: Vec<String> = paths.filter_map(Result::ok).map(|path| path.to_string_lossy().into_owned()).collect();
+    if files.is_empty() { return Err(CheckError::new(ErrorKind::Missing, "No files match the pattern")); }
+
+    Ok(files)
+  }
+
+  /// Age of timestamp in `file` in seconds, with a message if it's over warning threshold. Fails if it's stale
+  fn age(&self, file: &str) -> Result<(i64, Option<String>), CheckError> {
+    let diff = (Utc::now() - timestamp(file, &self.source)?).num_seconds();
+
+    if -diff > self.max_future {
+      let message = format!("Timestamp is {}s in the future, more than max-future of {}s, is the clock skewed?", -diff, self.max_future);
+      Err(CheckError::new(ErrorKind::Assertion, message))
+    } else if diff > self.timeout {
+      Err(CheckError::new(ErrorKind::Assertion, format!("Diff larger then timeout by {}", diff - self.timeout)))
+    } else {
+      let warning = self.warning.filter(|warning| diff > *warning);
+      Ok((diff, warning.map(|warning| format!("Diff larger then warning threshold by {}", diff - warning))))
+    }
+  }
+}
+
+fn config_error(message: String) -> CheckError {
+  CheckError::new(ErrorKind::Config, message)
+}
+
+/// Writes current time to `file` in `format` of `TimestampType`. File is replaced atomically, so the check never reads
+/// it half-written
+pub fn write_heartbeat(file: &str, format: Option<&str>) -> Result<(), String> {
+  if is_pattern(file) { return Err(format!("{}: Can't write to glob pattern", file)); }
+  let format = format.map_or(Ok(Format::Auto), Format::parse)?;
+  let content = format.format(Utc::now())?;
+
+  // Hidden temp file, so it doesn't match patterns like `health.*`
+  let path = Path::new(file);
+  let name = path.file_name().ok_or_else(|| format!("{}: Not a file path", file))?;
+  let temp = path.with_file_name(format!(".{}.{}.tmp", name.to_string_lossy(), std::process::id()));
+
+  std::fs::write(&temp, content + "\n").and_then(|_| std::fs::rename(&temp, path)).map_err(|e| format!("{}: {}", file, e))
+}
+
+fn is_pattern(target: &str) -> bool {
+  target.contains(['*', '?', '['])
+}
+
+fn timestamp(file: &str, source: &Source) -> Result<DateTime<Utc>, CheckError> {
+  match source {
+    Source::Content(format) => {
+      let content = std::fs::read_to_string(file).map_err(read_error)?;
+      parse_timestamp(&content, format).map_err(|e| CheckError::new(ErrorKind::Protocol, e))
+    }
+    Source::Mtime => Ok(std::fs::metadata(file).and_then(|metadata| metadata.modified()).map_err(read_error)?.into()),
+  }
+}
+
+fn read_error(err: std::io::Error) -> CheckError {
+  match err.kind() {
+    std::io::ErrorKind::NotFound => CheckError::new(ErrorKind::Missing, "File does not exist"),
+    _ => CheckError::new(ErrorKind::Io, err.to_string()),
+  }
+}
+
+/// Format of timestamp in file, set with `format` option
+#[derive(Debug, Clone, PartialEq)]
+enum Format {
+  /// Epoch seconds, millis or micros by number of digits, or RFC3339 date
+  Auto,
+  /// Epoch seconds, can be fractional like `1700000000.25`
+  Seconds,
+  Millis,
+  Micros,
+  /// Date like `2026-10-16T10:00:00Z`
+  Rfc3339,
+  /// Custom chrono strftime pattern, e.g. `%Y-%m-%d %H:%M:%S`. Dates without time zone are UTC
+  Strftime(String),
+}
+
+impl Format {
+  fn parse(format: &str) -> Result<Format, String> {
+    match format {
+      "auto" => Ok(Format::Auto),
+      "seconds" => Ok(Format::Seconds),
+      "millis" => Ok(Format::Millis),
+      "micros" => Ok(Format::Micros),
+      "rfc3339" => Ok(Format::Rfc3339),
+      pattern if pattern.contains('%') => Ok(Format::Strftime(pattern.to_owned())),
+      _ => Err(format!("Unknown timestamp format `{}`, expected auto, seconds, millis, micros, rfc3339 or strftime pattern", format)),
+    }
+  }
+
+  /// Formats `time` so it's parsed back in this format, `Auto` is epoch seconds
+  fn format(&self, time: DateTime<Utc>) -> Result<String, String> {
+    match self {
+      Format::Auto | Format::Seconds => Ok(time.timestamp().to_string()),
+      Format::Millis => Ok(time.timestamp_millis().to_string()),
+      Format::Micros => Ok((time.timestamp() * 1_000_000 + time.timestamp_subsec_micros() as i64).to_string()),
+      Format::Rfc3339 => Ok(time.to_rfc3339_opts(SecondsFormat::Secs, true)),
+      Format::Strftime(pattern) => {
+        let mut formatted = String::new();
+        write!(formatted, "{}", time.format(pattern)).map_err(|_| format!("Invalid timestamp format `{}`", pattern))?;
+
+        Ok(formatted)
+      }
+    }
+  }
+
+  fn describe(&self) -> String {
+    match self {
+      Format::Auto => "epoch seconds, millis, micros or RFC3339 date".to_owned(),
+      Format::Seconds => "epoch seconds".to_owned(),
+      Format::Millis => "epoch millis".to_owned(),
+      Format::Micros => "epoch micros".to_owned(),
+      Format::Rfc3339 => "RFC3339 date".to_owned(),
+      Format::Strftime(pattern) => format!("date in `{}` format", pattern),
+    }
+  }
+}
+
+/// Parses `content` of timestamp file in `format`, surrounding spaces and newlines are trimmed
+fn parse_timestamp(content: &str, format: &Format) -> Result<DateTime<Utc>, String> {
+  let content = content.trim();
+  if content.is_empty() { return Err("Timestamp file is empty".to_owned()); }
+
+  let parsed = match format {
+    Format::Auto => return parse_timestamp(content, &detect_format(content)).map_err(|_| unparseable(content, format)),
+    Format::Seconds => parse_epoch(content, 0),
+    Format::Millis => parse_epoch(content, 3),
+    Format::Micros => parse_epoch(content, 6),
+    Format::Rfc3339 => DateTime::parse_from_rfc3339(content).ok().map(|timestamp| timestamp.with_timezone(&Utc)),
+    Format::Strftime(pattern) => DateTime::parse_from_str(content, pattern).map(|timestamp| timestamp.with_timezone(&Utc))
+      .or_else(|_| NaiveDateTime::parse_from_str(content, pattern).map(|timestamp| Utc.from_utc_datetime(&timestamp)))
+      .ok(),
+  };
+
+  parsed.ok_or_else(|| unparseable(content, format))
+}
+
+fn unparseable(content: &str, format: &Format) -> String {
+  let shown: String = content.chars().take(40).collect();
+  let ellipsis = if shown.len() < content.len() { "..." } else { "" };
+
+  format!("Unparseable timestamp `{}{}`, expected {}", shown, ellipsis, format.describe())
+}
+
+/// Detects epoch unit by number of digits, so millis are not mistaken for seconds far in the future
+fn detect_format(content: &str) -> Format {
+  if !content.chars().all(|c| c.is_ascii_digit() || c == '.') { return Format::Rfc3339; }
+
+  match content.split('.').next().unwrap_or_default().len() {
+    0..=11 => Format::Seconds,
+    12..=14 => Format::Millis,
+    _ => Format::Micros,
+  }
+}
+
+/// Parses epoch time in units with `precision` digits after seconds, e.g. `3` for millis. Value can have fraction
+fn parse_epoch(content: &str, precision: u32) -> Option<DateTime<Utc>> {
+  let (integer, fraction) = content.split_once('.').unwrap_or((content, ""));
+  let is_number = |value: &str| value.chars().all(|c| c.is_ascii_digit());
+  if integer.is_empty() || !is_number(integer) || !is_number(fraction) { return None; }
+
+  let (integer, scale) = (integer.parse::<i64>().ok()?, 10_i64.pow(precision));
+  let fraction: String = fraction.chars().chain(std::iter::repeat('0')).take(9 - precision as usize).collect();
+  let nanos = (integer % scale) * 10_i64.pow(9 - precision) + fraction.parse::<i64>().unwrap_or(0);
+
+  Utc.timestamp_opt(integer / scale, nanos as u32).single()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn time(seconds: i64, nanos: u32) -> DateTime<Utc> {
+    Utc.timestamp_opt(seconds, nanos).unwrap()
+  }
+
+  #[test]
+  fn detect_format_by_digits() {
+    assert_eq!(detect_format("1700000000"), Format::Seconds);
+    assert_eq!(detect_format("99999999999"), Format::Seconds);
+    assert_eq!(detect_format("1700000000.25"), Format::Seconds);
+    assert_eq!(detect_format("170000000000"), Format::Millis);
+    assert_eq!(detect_format("99999999999999"), Format::Millis);
+    assert_eq!(detect_format("123456789012345"), Format::Micros);
+    assert_eq!(detect_format("1700000000000000"), Format::Micros);
+    assert_eq!(detect_format("2026-10-16T10:00:00Z"), Format::Rfc3339);
+  }
+
+  #[test]
+  fn parse_epoch_units() {
+    assert_eq!(parse_epoch("1700000000", 0), Some(time(1_700_000_000, 0)));
+    assert_eq!(parse_epoch("1700000000123", 3), Some(time(1_700_000_000, 123_000_000)));
+    assert_eq!(parse_epoch("1700000000123456", 6), Some(time(1_700_000_000, 123_456_000)));
+  }
+
+  #[test]
+  fn parse_epoch_pads_fraction() {
+    assert_eq!(parse_epoch("1700000000.25", 0), Some(time(1_700_000_000, 250_000_000)));
+    assert_eq!(parse_epoch("1700000000123.5", 3), Some(time(1_700_000_000, 123_500_000)));
+    assert_eq!(parse_epoch("1700000000.1234567891", 0), Some(time(1_700_000_000, 123_456_789)));
+  }
+
+  #[test]
+  fn parse_epoch_rejects_non_numbers() {
+    assert_eq!(parse_epoch("", 0), None);
+    assert_eq!(parse_epoch(".5", 0), None);
+    assert_eq!(parse_epoch("17e9", 0), None);
+    assert_eq!(parse_epoch("1.2.3", 0), None);
+    assert_eq!(parse_epoch("-1", 0), None);
+  }
+
+  #[test]
+  fn parse_timestamp_trims_and_detects() {
+    assert_eq!(parse_timestamp(" 1700000000\n", &Format::Auto), Ok(time(1_700_000_000, 0)));
+    assert_eq!(parse_timestamp("1700000000250\n", &Format::Auto), Ok(time(1_700_000_000, 250_000_000)));
+    assert_eq!(parse_timestamp("2023-11-15T00:13:20+02:00", &Format::Auto), Ok(time(1_700_000_000, 0)));
+  }
+
+  #[test]
+  fn parse_timestamp_empty() {
+    assert_eq!(parse_timestamp(" \n", &Format::Auto), Err("Timestamp file is empty".to_owned()));
+    assert_eq!(parse_timestamp("", &Format::Seconds), Err("Timestamp file is empty".to_owned()));
+  }
+
+  #[test]
+  fn parse_timestamp_unparseable() {
+    assert_eq!(
+      parse_timestamp("yesterday", &Format::Auto),
+      Err("Unparseable timestamp `yesterday`, expected epoch seconds, millis, micros or RFC3339 date".to_owned()),
+    );
+    assert_eq!(
+      parse_timestamp("1700000000", &Format::Rfc3339),
+      Err("Unparseable timestamp `1700000000`, expected RFC3339 date".to_owned()),
+    );
+  }
+
+  #[test]
+  fn parse_timestamp_strftime() {
+    let without_zone = Format::Strftime("%Y-%m-%d %H:%M:%S".to_owned());
+    assert_eq!(parse_timestamp("2023-11-14 22:13:20", &without_zone), Ok(time(1_700_000_000, 0)));
+
+    let with_zone = Format::Strftime("%Y-%m-%d %H:%M:%S %z".to_owned());
+    assert_eq!(parse_timestamp("2023-11-15 00:13:20 +0200", &with_zone), Ok(time(1_700_000_000, 0)));
+  }
+
+  #[test]
+  fn read_errors() {
+    let missing = std::io::Error::new(std::io::ErrorKind::NotFound, "No such file or directory");
+    let denied = std::io::Error::new(std::io::ErrorKind::PermissionDenied, "Permission denied");
+
+    assert_eq!(read_error(missing).kind, ErrorKind::Missing);
+    assert_eq!(read_error(denied).kind, ErrorKind::Io);
+  }
+
+  #[test]
+  fn format_timestamp() {
+    let time = time(1_700_000_000, 250_000_000);
+
+    assert_eq!(Format::Auto.format(time), Ok("1700000000".to_owned()));
+    assert_eq!(Format::Millis.format(time), Ok("1700000000250".to_owned()));
+    assert_eq!(Format::Micros.format(time), Ok("1700000000250000".to_owned()));
+    assert_eq!(Format::Rfc3339.format(time), Ok("2023-11-14T22:13:20Z".to_owned()));
+    assert_eq!(Format::Strftime("%Y-%m-%d %H:%M:%S".to_owned()).format(time), Ok("2023-11-14 22:13:20".to_owned()));
+  }
+
+  #[test]
+  fn format_invalid_strftime() {
+    assert!(Format::Strftime("%Q".to_owned()).format(time(0, 0)).is_err());
+  }
+
+  #[test]
+  fn format_parses_back() {
+    let time = time(1_700_000_000, 0);
+    let formats = [
+      Format::Auto, Format::Seconds, Format::Millis, Format::Micros, Format::Rfc3339, Format::parse("%d.%m.%Y %H:%M:%S").unwrap(),
+    ];
+
+    for format in &formats {
+      assert_eq!(parse_timestamp(&format.format(time).unwrap(), format), Ok(time), "{:?}", format);
+    }
+  }
+}
@@ -0,0 +1,116 @@
+use crate::Severity;
+
+/// Failure of a check
+#[derive(Debug, Clone)]
+pub struct CheckError {
+  pub kind: ErrorKind,
+  /// Name of the failed check and its target, set by `run_checks`
+  pub check: String,
+  pub target: String,
+  pub message: String,
+}
+
+/// Stage at which a check failed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorKind {
+  /// Check is misconfigured, so health of the target is unknown
+  Config,
+  Dns,
+  Connect,
+  Auth,
+  Tls,
+  Timeout,
+  /// Target doesn't exist, e.g. heartbeat file was never written
+  Missing,
+  /// Target exists, but can't be read, e.g. because of its permissions
+  Io,
+  /// Target responded with something unexpected
+  Protocol,
+  /// Target responded, but is unhealthy, e.g. timestamp is too old or HTTP status is 5xx
+  Assertion,
+}
+
+impl CheckError {
+  pub fn new(kind: ErrorKind, message: impl Into<String>) -> Self {
+    CheckError { kind, check: String::new(), target: String::new(), message: message.into() }
+  }
+
+  pub fn severity(&self) -> Severity {
+    match self.kind {
+      ErrorKind::Config => Severity::Unknown,
+      _ => Severity::Critical,
+    }
+  }
+}
+
+impl ErrorKind {
+  /// Likely cause of the failure, shown with `RunOptions::explain`
+  pub fn hint(&self) -> &'static str {
+    match self {
+      ErrorKind::Config => "is the URL correct?",
+      ErrorKind::Dns => "is the host name correct and resolvable from here?",
+      ErrorKind::Connect => "is the container up?",
+      ErrorKind::Auth => "are the credentials correct?",
+      ErrorKind::Tls => "does the server use TLS, with a certificate valid for this host?",
+      ErrorKind::Timeout => "is the server overloaded, or is a firewall dropping packets?",
+      ErrorKind::Missing => "has the app started, and is the path the same as where it writes?",
+      ErrorKind::Io => "can this user read the file, and is the volume mounted?",
+      ErrorKind::Protocol => "does this port serve the expected protocol?",
+      ErrorKind::Assertion => "the server responded, but is unhealthy, see its logs",
+    }
+  }
+
+  pub fn name(&self) -> &'static str {
+    match self {
+      ErrorKind::Config => "config",
+      ErrorKind::Dns => "dns",
+      ErrorKind::Connect => "connect",
+      ErrorKind::Auth => "auth",
+      ErrorKind::Tls => "tls",
+      ErrorKind::Timeout => "timeout",
+      ErrorKind::Missing => "missing",
+      ErrorKind::Io => "io",
+      ErrorKind::Protocol => "protocol",
+      ErrorKind::Assertion => "assertion",
+    }
+  }
+}
+
+impl std::fmt::Display for CheckError {
+  fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+    f.write_str(&self.message)
+  }
+}
+
+impl std::error::Error for CheckError {}
+
+/// Converts library error to `CheckError`, categorized by IO errors in its sources, or as `fallback`
+pub(crate) fn check_error(err: &(dyn std::error::Error + 'static), fallback: ErrorKind) -> CheckError {
+  let mut source = Some(err);
+  while let Some(source_err) = source {
+    if let Some(kind) = source_err.downcast_ref::<std::io::Error>().and_then(io_error_kind) {
+      return CheckError::new(kind, err.to_string());
+    }
+
+    source = source_err.source();
+  }
+
+  CheckError::new(fallback, err.to_string())
+}
+
+pub(crate) fn io_error_kind(err: &std::io::Error) -> Option<ErrorKind> {
+  use std::io::ErrorKind as Io;
+
+  match err.kind() {
+    Io::TimedOut | Io::WouldBlock => Some(ErrorKind::Timeout),
+    Io::ConnectionRefused | Io::ConnectionReset | Io::ConnectionAborted | Io::NotConnected | Io::AddrNotAvailable
+    | Io::HostUnreachable | Io::NetworkUnreachable => Some(ErrorKind::Connect),
+    _ if is_dns_error(&err.to_string()) => Some(ErrorKind::Dns),
+    _ => None,
+  }
+}
+
+/// Resolver errors are not distinguished by `io::ErrorKind`, only by message of `getaddrinfo`
+pub(crate) fn is_dns_error(message: &str) -> bool {
+  message.contains("failed to lookup address") || message.contains("Name or service not known")
+}
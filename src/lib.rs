@@ -0,0 +1,24 @@
+//! Health checks of applications and their dependencies: timestamp files, AMQP, PostgreSQL, Redis and HTTP.
+//!
+//! Checks are built from an `Instance` by their `CheckType`, e.g. `Job::new(&PostgresType, instance)`,
+//! or loaded from a TOML file with `load_config` using types from a `Registry`, and run in parallel with `run_checks`.
+//! Results can be rendered with `json_report`, `nagios_report` or as Prometheus `Metrics`.
+
+mod check;
+pub mod checks;
+mod common;
+mod config;
+mod error;
+mod report;
+mod runner;
+mod state;
+mod trace;
+
+pub use check::{Check, CheckOption, CheckType, Instance, Metric, Outcome, Registry, Severity};
+pub use common::{parse_duration, parse_limit, redact, redact_url};
+pub use config::{load_config, Config, Overrides};
+pub use error::{CheckError, ErrorKind};
+pub use report::{has_failures, json_report, nagios_report, Metrics};
+pub use runner::{run_checks, CheckResult, Job, RunOptions, Waiver};
+pub use state::{History, State};
+pub use trace::{Stage, Trace};
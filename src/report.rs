@@ -0,0 +1,390 @@
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use crate::{CheckResult, Metric, Severity, Waiver};
+
+pub fn has_failures(results: &[CheckResult]) -> bool {
+  results.iter().any(CheckResult::is_failure)
+}
+
+/// Single status line with performance data. Metrics are prefixed with check name if there are several checks
+pub fn nagios_report(results: &[CheckResult]) -> String {
+  let severity = results.iter().map(CheckResult::severity).max().unwrap_or_default();
+  let state = match severity {
+    Severity::Ok => "OK",
+    Severity::Warning => "WARNING",
+    Severity::Critical => "CRITICAL",
+    Severity::Unknown => "UNKNOWN",
+  };
+
+  let problems: Vec<String> = results.iter()
+    .filter(|result| result.severity() != Severity::Ok || result.waiver.is_some())
+    .map(|result| match (&result.waiver, &result.result) {
+      (Some(Waiver::Maintenance(reason)), _) => format!("{}: Maintenance: {}", result.name, reason),
+      (Some(Waiver::Starting), Err(err)) => format!("{}: Starting: {}", result.name, err),
+      (_, Ok(outcome)) => format!("{}: {}", result.name, outcome.message),
+      (_, Err(err)) => format!("{}: {}", result.name, err),
+    })
+    .collect();
+  let summary = if problems.is_empty() {
+    let names: Vec<&str> = results.iter().map(|result| result.name.as_str()).collect();
+    format!("All checks passed: {}", names.join(", "))
+  } else {
+    problems.join(", ")
+  };
+
+  let mut perfdata = Vec::new();
+  for result in results {
+    let prefix = if results.len() > 1 { format!("{}_", result.name) } else { String::new() };
+    let time = Metric { name: "time", value: result.duration.as_secs_f64(), unit: "s", warning: None, critical: None };
+    let metrics = result.result.as_ref().map(|outcome| outcome.metrics.as_slice()).unwrap_or_default();
+    // Checks of response time report `time` with thresholds themselves
+    let time = Some(&time).filter(|_| !metrics.iter().any(|metric| metric.name == "time"));
+
+    for metric in time.into_iter().chain(metrics) {
+      let threshold = |value: Option<f64>| value.map(|v| v.to_string()).unwrap_or_default();
+      let data = format!(
+        "{}{}={}{};{};{}", prefix, metric.name, metric.value, metric.unit, threshold(metric.warning), threshold(metric.critical)
+      );
+      perfdata.push(data.trim_end_matches(';').to_owned());
+    }
+  }
+
+  format!("HEALTHCHECK {} - {} | {}", state, summary.replace('|', "/"), perfdata.join(" "))
+}
+
+pub fn json_report(results: &[CheckResult]) -> serde_json::Value {
+  let checks: Vec<_> = results.iter().map(|result| {
+    let mut check = serde_json::json!({
+      "name": result.name,
+      "type": result.kind,
+      "target": result.target,
+      "status": result.status(),
+      "category": result.result.as_ref().err().map(|err| err.kind.name()),
+      "health": result.severity().health(),
+      "duration_ms": result.duration.as_millis() as u64,
+      "error": match &result.result {
+        Ok(outcome) if outcome.message.is_empty() => None,
+        Ok(outcome) => Some(outcome.message.clone()),
+        Err(err) => Some(err.to_string()),
+      },
+    });
+
+    if !result.stages.is_empty() {
+      check["stages"] = result.stages.iter().map(|stage| serde_json::json!({
+        "name": stage.name,
+        "duration_ms": (stage.duration.as_secs_f64() * 1e6).round() / 1e3,
+        "detail": stage.detail,
+        "failed": stage.failed,
+      })).collect();
+
+      if let Err(err) = &result.result { check["hint"] = err.kind.hint().into(); }
+    }
+    if let Some(Waiver::Maintenance(reason)) = &result.waiver { check["maintenance"] = reason.as_str().into(); }
+    if let Some(history) = &result.history {
+      let now = chrono::offset::Utc::now().timestamp();
+      check["consecutive_failures"] = history.consecutive_failures.into();
+      check["seconds_since_last_success"] = serde_json::json!(history.last_success.map(|last_success| now - last_success));
+    }
+
+    check
+  }).collect();
+  let status = if has_failures(results) {
+    "failed"
+  } else if results.iter().any(|result| matches!(result.waiver, Some(Waiver::Maintenance(_)))) {
+    "maintenance"
+  } else if results.iter().any(|result| result.waiver == Some(Waiver::Starting)) {
+    "starting"
+  } else {
+    "ok"
+  };
+  let health = results.iter().map(CheckResult::severity).max().unwrap_or_default().health();
+
+  serde_json::json!({ "status": status, "health": health, "checks": checks })
+}
+
+/// Latest state of every check instance, rendered in Prometheus text format
+#[derive(Debug, Default)]
+pub struct Metrics {
+  /// Keyed by check name and target
+  checks: BTreeMap<(String, String), CheckMetrics>,
+}
+
+#[derive(Debug, Default)]
+struct CheckMetrics {
+  up: bool,
+  /// Skipped or forced to pass for maintenance, other values are kept from the last real run
+  maintenance: bool,
+  duration: Duration,
+  last_success: Option<i64>,
+  consecutive_failures: Option<u32>,
+}
+
+impl Metrics {
+  pub fn record(&mut self, results: &[CheckResult]) {
+    let now = chrono::offset::Utc::now().timestamp();
+
+    for result in results {
+      let metrics = self.checks.entry((result.name.clone(), result.target.clone())).or_default();
+      metrics.maintenance = matches!(result.waiver, Some(Waiver::Maintenance(_)));
+      if metrics.maintenance { continue; }
+
+      metrics.up = result.result.is_ok();
+      metrics.duration = result.duration;
+      if metrics.up { metrics.last_success = Some(now); }
+      if let Some(history) = &result.history {
+        metrics.last_success = history.last_success;
+        metrics.consecutive_failures = Some(history.consecutive_failures);
+      }
+    }
+  }
+
+  /// Restores metrics from `render` output, e.g. written to a file by the previous run, so they are kept across runs
+  /// of separate processes. Lines that are not metrics of checks are skipped
+  pub fn parse(text: &str) -> Metrics {
+    let mut metrics = Metrics::default();
+
+    for line in text.lines().filter(|line| !line.starts_with('#')) {
+      let parsed = line.split_once('{').and_then(|(name, labels)| {
+        let (check, labels) = parse_label(labels, "check")?;
+        let (target, labels) = parse_label(labels.strip_prefix(',')?, "target")?;
+        let value = labels.strip_prefix("} ")?;
+
+        Some((name, check, target, value))
+      });
+      let (name, check, target, value) = match parsed {
+        Some(parsed) => parsed,
+        None => continue,
+      };
+
+      let check = metrics.checks.entry((check, target)).or_default();
+      match name {
+        "healthcheck_up" => check.up = value == "1",
+        "healthcheck_maintenance" => check.maintenance = value == "1",
+        "healthcheck_duration_seconds" => {
+          check.duration = value.parse().ok().and_then(|value| Duration::try_from_secs_f64(value).ok()).unwrap_or_default();
+        }
+        "healthcheck_last_success_timestamp" => check.last_success = value.parse().ok(),
+        "healthcheck_consecutive_failures" => check.consecutive_failures = value.parse().ok(),
+        _ => {}
+      }
+    }
+
+    metrics
+  }
+
+  /// Drops metrics of checks that are not in `results`, e.g. restored ones of checks that are no longer enabled
+  pub fn retain(&mut self, results: &[CheckResult]) {
+    self.checks.retain(|(check, target), _| results.iter().any(|result| result.name == *check && result.target == *target));
+  }
+
+  pub fn render(&self) -> String {
+    let mut out = String::new();
+    let mut family = |name: &str, help: &str, value: &dyn Fn(&CheckMetrics) -> Option<String>| {
+      out += &format!("# HELP {} {}\n# TYPE {} gauge\n", name, help, name);
+
+      for ((check, target), metrics) in &self.checks {
+        if let Some(value) = value(metrics) {
+          out += &format!("{}{{check=\"{}\",target=\"{}\"}} {}\n", name, escape_label(check), escape_label(target), value);
+        }
+      }
+    };
+
+    family("healthcheck_up", "Whether the last run of the check succeeded", &|m| Some((m.up as u8).to_string()));
+    family(
+      "healthcheck_maintenance", "Whether the check is skipped or forced to pass for maintenance, other metrics are of its last run",
+      &|m| Some((m.maintenance as u8).to_string()),
+    );
+    family("healthcheck_duration_seconds", "Duration of the last run of the check", &|m| Some(m.duration.as_secs_f64().to_string()));
+    family(
+      "healthcheck_last_success_timestamp", "Unix time of the last successful run of the check",
+      &|m| m.last_success.map(|timestamp| timestamp.to_string()),
+    );
+    family(
+      "healthcheck_consecutive_failures", "Number of consecutive failures of the check, tracked with state file",
+      &|m| m.consecutive_failures.map(|failures| failures.to_string()),
+    );
+
+    out
+  }
+}
+
+fn escape_label(value: &str) -> String {
+  value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+/// Reads `label="value"` at the start of `text`, returns unescaped value and the rest after it
+fn parse_label<'a>(text: &'a str, label: &str) -> Option<(String, &'a str)> {
+  let text = text.strip_prefix(label)?.strip_prefix("=\"")?;
+  let (mut value, mut chars) = (String::new(), text.char_indices());
+
+  while let Some((index, c)) = chars.next() {
+    match c {
+      '"' => return Some((value, &text[index + 1..])),
+      '\\' => value.push(match chars.next()?.1 { 'n' => '\n', c => c }),
+      c => value.push(c),
+    }
+  }
+
+  None
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::{CheckError, ErrorKind, History, Outcome, Stage};
+
+  fn result(name: &str, result: Result<Outcome, CheckError>) -> CheckResult {
+    CheckResult {
+      name: name.to_owned(), kind: "http", target: String::new(), duration: Duration::from_millis(12), result,
+      stages: Vec::new(), history: None, waiver: None,
+    }
+  }
+
+  fn time(value: f64) -> Metric {
+    Metric { name: "time", value, unit: "s", warning: Some(0.5), critical: Some(1.0) }
+  }
+
+  #[test]
+  fn nagios_report_passed() {
+    let results = [result("http", Ok(Outcome::default()))];
+
+    assert_eq!(nagios_report(&results), "HEALTHCHECK OK - All checks passed: http | time=0.012s");
+  }
+
+  #[test]
+  fn nagios_report_time_thresholds() {
+    let results = [result("http", Ok(Outcome { metrics: vec![time(0.25)], ..Outcome::default() }))];
+
+    assert_eq!(nagios_report(&results), "HEALTHCHECK OK - All checks passed: http | time=0.25s;0.5;1");
+  }
+
+  #[test]
+  fn nagios_report_worst_state_and_prefixes() {
+    let degraded = Outcome { severity: Severity::Warning, message: "Slow".to_owned(), metrics: vec![time(0.75)] };
+    let results = [
+      result("http", Ok(degraded)),
+      result("redis", Err(CheckError::new(ErrorKind::Connect, "Connection refused | reset"))),
+    ];
+
+    assert_eq!(
+      nagios_report(&results),
+      "HEALTHCHECK CRITICAL - http: Slow, redis: Connection refused / reset | http_time=0.75s;0.5;1 redis_time=0.012s",
+    );
+  }
+
+  #[test]
+  fn nagios_report_config_error_is_unknown() {
+    let results = [result("http", Err(CheckError::new(ErrorKind::Config, "Invalid URL")))];
+
+    assert!(nagios_report(&results).starts_with("HEALTHCHECK UNKNOWN - http: Invalid URL"));
+  }
+
+  #[test]
+  fn nagios_report_waived() {
+    let mut starting = result("http", Err(CheckError::new(ErrorKind::Connect, "Connection refused")));
+    starting.waiver = Some(Waiver::Starting);
+
+    assert!(nagios_report(&[starting]).starts_with("HEALTHCHECK OK - http: Starting: Connection refused"));
+  }
+
+  #[test]
+  fn json_report_checks() {
+    let degraded = Outcome { severity: Severity::Warning, message: "Slow".to_owned(), metrics: Vec::new() };
+    let results = [result("http", Ok(degraded)), result("redis", Err(CheckError::new(ErrorKind::Timeout, "Timed out")))];
+
+    assert_eq!(json_report(&results), serde_json::json!({
+      "status": "failed",
+      "health": "unhealthy",
+      "checks": [
+        {
+          "name": "http", "type": "http", "target": "", "status": "degraded", "category": null, "health": "degraded",
+          "duration_ms": 12, "error": "Slow",
+        },
+        {
+          "name": "redis", "type": "http", "target": "", "status": "timeout", "category": "timeout", "health": "unhealthy",
+          "duration_ms": 12, "error": "Timed out",
+        },
+      ],
+    }));
+  }
+
+  #[test]
+  fn json_report_waived() {
+    let mut skipped = result("http", Ok(Outcome::default()));
+    skipped.waiver = Some(Waiver::Maintenance("Migration".to_owned()));
+    let mut starting = result("redis", Err(CheckError::new(ErrorKind::Connect, "Connection refused")));
+    starting.waiver = Some(Waiver::Starting);
+
+    let report = json_report(&[skipped, starting.clone()]);
+    assert_eq!((&report["status"], &report["health"]), (&"maintenance".into(), &"healthy".into()));
+    assert_eq!(report["checks"][0]["maintenance"], "Migration");
+    assert_eq!(report["checks"][1]["status"], "starting");
+    assert_eq!(json_report(&[starting])["status"], "starting");
+  }
+
+  #[test]
+  fn json_report_history_and_stages() {
+    let mut failed = result("http", Err(CheckError::new(ErrorKind::Connect, "Connection refused")));
+    failed.history = Some(History { consecutive_failures: 3, ..History::default() });
+    failed.stages = vec![Stage { name: "connect", duration: Duration::from_micros(1500), detail: "refused".to_owned(), failed: true }];
+
+    let check = &json_report(&[failed])["checks"][0];
+    assert_eq!(check["consecutive_failures"], 3);
+    assert_eq!(check["seconds_since_last_success"], serde_json::Value::Null);
+    assert_eq!(check["stages"][0]["duration_ms"], 1.5);
+    assert_eq!(check["hint"], ErrorKind::Connect.hint());
+  }
+
+  #[test]
+  fn metrics_keep_last_run_during_maintenance() {
+    let mut metrics = Metrics::default();
+    metrics.record(&[result("http", Err(CheckError::new(ErrorKind::Connect, "Connection refused")))]);
+
+    let mut skipped = result("http", Ok(Outcome::default()));
+    skipped.waiver = Some(Waiver::Maintenance("Migration".to_owned()));
+    metrics.record(&[skipped]);
+
+    let rendered = metrics.render();
+    assert!(rendered.contains("healthcheck_up{check=\"http\",target=\"\"} 0\n"));
+    assert!(rendered.contains("healthcheck_maintenance{check=\"http\",target=\"\"} 1\n"));
+    assert!(!rendered.contains("healthcheck_last_success_timestamp{"));
+  }
+
+  fn targeted(name: &str, target: &str) -> CheckResult {
+    CheckResult { target: target.to_owned(), ..result(name, Ok(Outcome::default())) }
+  }
+
+  #[test]
+  fn metrics_escape_labels() {
+    let mut metrics = Metrics::default();
+    metrics.record(&[targeted("http", "C:\\dir \"a\"\nb")]);
+
+    assert!(metrics.render().contains("healthcheck_up{check=\"http\",target=\"C:\\\\dir \\\"a\\\"\\nb\"} 1\n"));
+  }
+
+  #[test]
+  fn metrics_parse_rendered() {
+    let mut metrics = Metrics::default();
+    metrics.record(&[targeted("http", "http://a/?q=\"x\""), result("redis", Err(CheckError::new(ErrorKind::Connect, "Refused")))]);
+    let rendered = metrics.render();
+
+    assert_eq!(Metrics::parse(&rendered).render(), rendered);
+  }
+
+  #[test]
+  fn metrics_keep_last_success_of_previous_run() {
+    let mut previous = Metrics::default();
+    previous.record(&[targeted("http", "http://a"), targeted("redis", "redis://b")]);
+
+    let mut metrics = Metrics::parse(&previous.render());
+    let failed = CheckResult { target: "http://a".to_owned(), ..result("http", Err(CheckError::new(ErrorKind::Connect, "Refused"))) };
+    metrics.retain(std::slice::from_ref(&failed));
+    metrics.record(&[failed]);
+
+    let rendered = metrics.render();
+    assert!(rendered.contains("healthcheck_up{check=\"http\",target=\"http://a\"} 0\n"));
+    assert!(rendered.contains("healthcheck_last_success_timestamp{check=\"http\",target=\"http://a\"} "));
+    assert!(!rendered.contains("redis"));
+  }
+}
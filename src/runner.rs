@@ -0,0 +1,279 @@
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
+
+use crate::common::redact;
+use crate::{CheckError, CheckType, ErrorKind, History, Instance, Outcome, Severity, Stage, Trace};
+
+/// Check of a single instance, to be run on its own thread
+pub struct Job {
+  pub name: String,
+  pub kind: &'static str,
+  pub target: String,
+  run: Box<JobFn>,
+}
+
+type JobFn = dyn FnOnce(&mut Trace) -> Result<Outcome, CheckError> + Send;
+
+impl Job {
+  /// Builds check of `instance` with defaults of its type, or a job that reports its unknown options
+  pub fn new(check_type: &dyn CheckType, instance: Instance) -> Job {
+    let kind = check_type.name();
+    let instance = instance.with_defaults(&check_type.default_instance());
+    if let Err(err) = check_options(check_type, &instance) { return Job::failed(instance.name, kind, err); }
+
+    let (name, target) = (instance.name.clone(), instance.target.clone());
+    let check = check_type.build(instance);
+
+    Job { name, kind, target, run: Box::new(move |trace| check.check(trace)) }
+  }
+
+  /// Builds jobs of `instances` of `check_type`. Instances that the type checks together with `CheckType::build_group`
+  /// are one job named after the type
+  pub fn all(check_type: &dyn CheckType, instances: Vec<Instance>) -> Vec<Job> {
+    let defaults = check_type.default_instance();
+    let instances: Vec<_> = instances.into_iter().map(|instance| instance.with_defaults(&defaults)).collect();
+
+    let valid = instances.iter().all(|instance| check_options(check_type, instance).is_ok());
+    let group = if instances.len() > 1 && valid { check_type.build_group(&instances) } else { None };
+    if let Some(check) = group {
+      let kind = check_type.name();
+      let target = instances.iter().map(|instance| instance.target.as_str()).collect::<Vec<_>>().join(", ");
+
+      return vec![Job { name: kind.to_owned(), kind, target, run: Box::new(move |trace| check.check(trace)) }];
+    }
+
+    instances.into_iter().map(|instance| Job::new(check_type, instance)).collect()
+  }
+
+  /// Job that reports `err` instead of running a check, e.g. when it is misconfigured
+  pub fn failed(name: String, kind: &'static str, err: CheckError) -> Job {
+    Job { name, kind, target: String::new(), run: Box::new(move |_| Err(err)) }
+  }
+
+  /// Job that passes without running a check, e.g. when it is in maintenance
+  pub fn skipped(name: String, kind: &'static str, target: String, message: String) -> Job {
+    let outcome = Outcome { severity: Severity::Ok, message, metrics: Vec::new() };
+
+    Job { name, kind, target, run: Box::new(move |_| Ok(outcome)) }
+  }
+}
+
+/// Fails on options of `instance` that its type doesn't know
+fn check_options(check_type: &dyn CheckType, instance: &Instance) -> Result<(), CheckError> {
+  let unknown = instance.options.keys().find(|option| !check_type.options().iter().any(|known| known.name == option.as_str()));
+
+  match unknown {
+    Some(option) => Err(CheckError::new(ErrorKind::Config, format!("Unknown option `{}` in config of `{}`", option, instance.name))),
+    None => Ok(()),
+  }
+}
+
+/// Options of `run_checks`
+#[derive(Debug, Clone, Default)]
+pub struct RunOptions {
+  /// Overall time limit, checks still running are reported as timed out
+  pub deadline: Option<Duration>,
+  /// Stop at the first failed check
+  pub fail_fast: bool,
+  /// Record stages of checks in `CheckResult::stages`
+  pub explain: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct CheckResult {
+  pub name: String,
+  pub kind: &'static str,
+  /// Target with credentials redacted
+  pub target: String,
+  pub duration: Duration,
+  pub result: Result<Outcome, CheckError>,
+  /// Empty without `RunOptions::explain`
+  pub stages: Vec<Stage>,
+  /// Set by `State::apply`
+  pub history: Option<History>,
+  /// Set for checks that are not counted as failures, e.g. during startup grace period
+  pub waiver: Option<Waiver>,
+}
+
+/// Reason why result of a check is not counted as failure
+#[derive(Debug, Clone, PartialEq)]
+pub enum Waiver {
+  /// Failed during startup grace period
+  Starting,
+  /// Skipped or forced to pass during maintenance, with its reason
+  Maintenance(String),
+}
+
+impl Waiver {
+  pub fn status(&self) -> &'static str {
+    match self {
+      Waiver::Starting => "starting",
+      Waiver::Maintenance(_) => "maintenance",
+    }
+  }
+}
+
+impl CheckResult {
+  pub fn status(&self) -> &'static str {
+    if let Some(waiver) = &self.waiver { return waiver.status(); }
+
+    match &self.result {
+      Ok(outcome) if outcome.severity == Severity::Warning => "degraded",
+      Ok(_) => "ok",
+      Err(err) if err.kind == ErrorKind::Config => "config",
+      Err(err) if err.kind == ErrorKind::Timeout => "timeout",
+      Err(_) => "failed",
+    }
+  }
+
+  pub fn severity(&self) -> Severity {
+    if self.waiver.is_some() { return Severity::Ok; }
+
+    match &self.result {
+      Ok(outcome) => outcome.severity,
+      Err(err) => err.severity(),
+    }
+  }
+
+  /// Failed and not waived
+  pub fn is_failure(&self) -> bool {
+    self.result.is_err() && self.waiver.is_none()
+  }
+}
+
+/// Runs `jobs` in parallel, each on its own thread. Results are in order of `jobs`, with credentials redacted
+pub fn run_checks(jobs: Vec<Job>, options: &RunOptions) -> Vec<CheckResult> {
+  let RunOptions { deadline, fail_fast, explain } = *options;
+
+  let (sender, receiver) = mpsc::channel();
+  let jobs: Vec<_> = jobs.into_iter().enumerate().map(|(index, job)| {
+    let (sender, run) = (sender.clone(), job.run);
+
+    std::thread::spawn(move || {
+      let started = Instant::now();
+      let mut trace = Trace { explain, ..Trace::default() };
+      let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| run(&mut trace)))
+        .unwrap_or_else(|_| Err(CheckError::new(ErrorKind::Protocol, "Check panicked")));
+
+      sender.send((index, result, trace.stages, started.elapsed())).ok();
+    });
+
+    (job.name, job.kind, job.target)
+  }).collect();
+  drop(sender);
+
+  // Results are kept in order of `jobs`, not in order of completion
+  let started = Instant::now();
+  let mut results = vec![None; jobs.len()];
+  let mut timed_out = false;
+  for _ in 0..jobs.len() {
+    let received = match deadline {
+      Some(deadline) => receiver.recv_timeout(deadline.saturating_sub(started.elapsed())).ok(),
+      None => receiver.recv().ok(),
+    };
+
+    let (index, result, stages, duration) = match received {
+      Some(received) => received,
+      None => { timed_out = true; break; }
+    };
+
+    let failed = result.is_err();
+    results[index] = Some((result, stages, duration));
+
+    if failed && fail_fast { break; }
+  }
+
+  let elapsed = started.elapsed();
+  jobs.into_iter().zip(results)
+    .filter_map(|((name, kind, target), result)| {
+      let (result, mut stages, duration) = match result {
+        Some(result) => result,
+        None if timed_out => {
+          (Err(CheckError::new(ErrorKind::Timeout, "Still running when deadline was reached")), Vec::new(), elapsed)
+        }
+        None => return None,
+      };
+      let target = redact(&target);
+      let result = result.map_err(|err| {
+        CheckError { check: name.clone(), target: target.clone(), message: redact(&err.message), ..err }
+      });
+      for stage in &mut stages { stage.detail = redact(&stage.detail); }
+
+      Some(CheckResult { name, kind, target, duration, result, stages, history: None, waiver: None })
+    })
+    .collect()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn job(name: &str, delay_ms: u64, result: Result<Outcome, CheckError>) -> Job {
+    let run = move |_: &mut Trace| {
+      std::thread::sleep(Duration::from_millis(delay_ms));
+      result
+    };
+
+    Job { name: name.to_owned(), kind: "http", target: format!("http://user:secret@{}", name), run: Box::new(run) }
+  }
+
+  fn failure(message: &str) -> Result<Outcome, CheckError> {
+    Err(CheckError::new(ErrorKind::Connect, message))
+  }
+
+  fn names(results: &[CheckResult]) -> Vec<&str> {
+    results.iter().map(|result| result.name.as_str()).collect()
+  }
+
+  #[test]
+  fn results_keep_order_of_jobs() {
+    let jobs = vec![job("slow", 50, Ok(Outcome::default())), job("fast", 0, failure("Connection refused"))];
+    let results = run_checks(jobs, &RunOptions::default());
+
+    assert_eq!(names(&results), vec!["slow", "fast"]);
+    assert!(results[0].result.is_ok());
+    assert!(results[1].result.is_err());
+  }
+
+  #[test]
+  fn errors_are_named_and_redacted() {
+    let results = run_checks(vec![job("db", 0, failure("password=hunter2 rejected"))], &RunOptions::default());
+    let err = results[0].result.as_ref().unwrap_err();
+
+    assert_eq!((err.check.as_str(), err.target.as_str()), ("db", "http://user:***@db"));
+    assert_eq!(err.message, "password=*** rejected");
+  }
+
+  #[test]
+  fn deadline_times_out_running_checks() {
+    let jobs = vec![job("fast", 0, Ok(Outcome::default())), job("hanging", 2000, Ok(Outcome::default()))];
+    let options = RunOptions { deadline: Some(Duration::from_millis(100)), ..RunOptions::default() };
+    let results = run_checks(jobs, &options);
+
+    assert_eq!(names(&results), vec!["fast", "hanging"]);
+    assert!(results[0].result.is_ok());
+    let err = results[1].result.as_ref().unwrap_err();
+    assert_eq!(err.kind, ErrorKind::Timeout);
+    assert_eq!(err.check, "hanging");
+  }
+
+  #[test]
+  fn fail_fast_stops_at_first_failure() {
+    let jobs = vec![job("slow", 2000, Ok(Outcome::default())), job("failed", 0, failure("Connection refused"))];
+    let options = RunOptions { fail_fast: true, ..RunOptions::default() };
+    let started = Instant::now();
+    let results = run_checks(jobs, &options);
+
+    assert!(started.elapsed() < Duration::from_secs(1));
+    assert_eq!(names(&results), vec!["failed"]);
+  }
+
+  #[test]
+  fn panics_are_failures() {
+    let run = |_: &mut Trace| -> Result<Outcome, CheckError> { panic!("broken check") };
+    let jobs = vec![Job { name: "broken".to_owned(), kind: "http", target: String::new(), run: Box::new(run) }];
+    let results = run_checks(jobs, &RunOptions::default());
+
+    assert_eq!(results[0].result.as_ref().unwrap_err().message, "Check panicked");
+  }
+}
@@ -0,0 +1,165 @@
+use std::collections::BTreeMap;
+
+use crate::{CheckError, CheckResult, ErrorKind, Outcome, Severity};
+
+/// History of a check across runs
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+pub struct History {
+  pub consecutive_failures: u32,
+  pub consecutive_successes: u32,
+  /// Reported state, changes only after enough consecutive failures or successes
+  pub failing: bool,
+  /// Unix time of the last successful run
+  pub last_success: Option<i64>,
+}
+
+/// Histories of checks by name, persisted in state file between runs
+#[derive(Debug, Default, serde::Serialize, serde::Deserialize)]
+pub struct State {
+  checks: BTreeMap<String, History>,
+}
+
+impl State {
+  /// Loads state from JSON file at `path`, missing file is an empty state
+  pub fn load(path: &str) -> Result<State, String> {
+    match std::fs::read_to_string(path) {
+      Ok(content) => serde_json::from_str(&content).map_err(|e| format!("{}: {}", path, e)),
+      Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(State::default()),
+      Err(err) => Err(format!("{}: {}", path, err)),
+    }
+  }
+
+  /// Replaces file at `path` atomically, so concurrent runs never read it half-written
+  pub fn save(&self, path: &str) -> Result<(), String> {
+    // Temp file of each process, so concurrent runs don't write to the same one
+    let temp = format!("{}.{}.tmp", path, std::process::id());
+    let content = serde_json::to_string(self).map_err(|e| e.to_string())?;
+
+    std::fs::write(&temp, content).and_then(|_| std::fs::rename(&temp, path)).map_err(|e| format!("{}: {}", path, e))
+  }
+
+  /// Records `results` at `now` and applies tolerance to them: check fails only after `fail_after` consecutive failures,
+  /// and failing check recovers only after `recover_after` consecutive successes. Config errors are never tolerated,
+  /// waived results are not recorded
+  pub fn apply(&mut self, results: &mut [CheckResult], fail_after: u32, recover_after: u32, now: i64) {
+    for result in results {
+      if result.waiver.is_some() { continue; }
+      let history = self.checks.entry(result.name.clone()).or_default();
+
+      match &result.result {
+        Ok(_) => {
+          history.consecutive_failures = 0;
+          history.consecutive_successes += 1;
+          history.last_success = Some(now);
+          if history.consecutive_successes >= recover_after { history.failing = false; }
+        }
+        Err(err) => {
+          history.consecutive_successes = 0;
+          history.consecutive_failures += 1;
+          if history.consecutive_failures >= fail_after || err.kind == ErrorKind::Config { history.failing = true; }
+        }
+      }
+
+      match &result.result {
+        Err(err) if !history.failing => {
+          let message = format!("Failure {} of {} tolerated: {}", history.consecutive_failures, fail_after, err);
+          result.result = Ok(Outcome { severity: Severity::Warning, message, metrics: Vec::new() });
+        }
+        Ok(_) if history.failing => {
+          let message = format!("Recovering, success {} of {}", history.consecutive_successes, recover_after);
+          let (check, target) = (result.name.clone(), result.target.clone());
+          result.result = Err(CheckError { check, target, ..CheckError::new(ErrorKind::Assertion, message) });
+        }
+        _ => {}
+      }
+
+      result.history = Some(history.clone());
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::time::Duration;
+
+  use crate::Waiver;
+
+  fn result(result: Result<Outcome, CheckError>) -> CheckResult {
+    CheckResult {
+      name: "redis".to_owned(), kind: "redis", target: String::new(), duration: Duration::from_millis(5), result,
+      stages: Vec::new(), history: None, waiver: None,
+    }
+  }
+
+  fn success() -> CheckResult {
+    result(Ok(Outcome::default()))
+  }
+
+  fn failure(kind: ErrorKind) -> CheckResult {
+    result(Err(CheckError::new(kind, "Connection refused".to_owned())))
+  }
+
+  fn run(state: &mut State, mut result: CheckResult, now: i64) -> CheckResult {
+    state.apply(std::slice::from_mut(&mut result), 3, 2, now);
+    result
+  }
+
+  #[test]
+  fn tolerates_failures_before_fail_after() {
+    let mut state = State::default();
+
+    for attempt in 1..=2 {
+      let result = run(&mut state, failure(ErrorKind::Connect), attempt);
+      assert_eq!(result.severity(), Severity::Warning);
+      assert_eq!(result.result.unwrap().message, format!("Failure {} of 3 tolerated: Connection refused", attempt));
+    }
+
+    let result = run(&mut state, failure(ErrorKind::Connect), 3);
+    assert!(result.result.is_err());
+    assert_eq!(result.history.unwrap().consecutive_failures, 3);
+  }
+
+  #[test]
+  fn recovers_after_recover_after_successes() {
+    let mut state = State::default();
+    for now in 1..=3 { run(&mut state, failure(ErrorKind::Connect), now); }
+
+    let result = run(&mut state, success(), 4);
+    assert_eq!(result.result.unwrap_err().to_string(), "Recovering, success 1 of 2");
+
+    let result = run(&mut state, success(), 5);
+    assert!(result.result.is_ok());
+    let history = result.history.unwrap();
+    assert!(!history.failing);
+    assert_eq!(history.last_success, Some(5));
+  }
+
+  #[test]
+  fn success_resets_failures() {
+    let mut state = State::default();
+    run(&mut state, failure(ErrorKind::Connect), 1);
+    run(&mut state, success(), 2);
+
+    let result = run(&mut state, failure(ErrorKind::Connect), 3);
+    assert_eq!(result.severity(), Severity::Warning);
+    assert_eq!(result.history.unwrap().consecutive_failures, 1);
+  }
+
+  #[test]
+  fn config_errors_fail_immediately() {
+    let mut state = State::default();
+
+    assert!(run(&mut state, failure(ErrorKind::Config), 1).result.is_err());
+  }
+
+  #[test]
+  fn waived_results_are_not_recorded() {
+    let mut state = State::default();
+    let waived = CheckResult { waiver: Some(Waiver::Starting), ..failure(ErrorKind::Connect) };
+
+    let waived = run(&mut state, waived, 1);
+    assert!(waived.history.is_none());
+    assert!(state.checks.is_empty());
+  }
+}
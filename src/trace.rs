@@ -0,0 +1,79 @@
+use std::time::{Duration, Instant};
+
+use crate::error::io_error_kind;
+use crate::{CheckError, ErrorKind};
+
+/// Stages of a check with their timings. Recorded only with `RunOptions::explain`
+#[derive(Debug, Default)]
+pub struct Trace {
+  pub(crate) explain: bool,
+  pub(crate) stages: Vec<Stage>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Stage {
+  pub name: &'static str,
+  pub duration: Duration,
+  /// Resolved or connected addresses, or error message if stage failed
+  pub detail: String,
+  pub failed: bool,
+}
+
+impl Trace {
+  /// Runs `f` as stage `name`
+  pub fn stage<T>(&mut self, name: &'static str, f: impl FnOnce() -> Result<T, CheckError>) -> Result<T, CheckError> {
+    let started = Instant::now();
+    let result = f();
+
+    if self.explain {
+      let detail = result.as_ref().err().map(|err| err.message.clone()).unwrap_or_default();
+      self.stages.push(Stage { name, duration: started.elapsed(), detail, failed: result.is_err() });
+    }
+
+    result
+  }
+
+  pub fn detail(&mut self, detail: String) {
+    if let Some(stage) = self.stages.last_mut() { stage.detail = detail; }
+  }
+
+  /// Resolves `host` and connects to it as separate `dns` and `connect` stages, before the client library does the same.
+  /// Does nothing without `RunOptions::explain`
+  pub fn connect(&mut self, host: &str, port: u16, timeout: Option<Duration>) -> Result<(), CheckError> {
+    use std::net::{TcpStream, ToSocketAddrs};
+
+    if !self.explain { return Ok(()); }
+
+    let addrs: Vec<_> = self.stage("dns", || {
+      let addrs = (host, port).to_socket_addrs()
+        .map_err(|e| CheckError::new(ErrorKind::Dns, format!("could not resolve {}: {}", host, e)))?;
+
+      Ok(addrs.collect())
+    })?;
+    self.detail(addrs.iter().map(ToString::to_string).collect::<Vec<_>>().join(", "));
+
+    let addr = self.stage("connect", || {
+      let mut last_err = CheckError::new(ErrorKind::Dns, format!("no addresses found for {}", host));
+
+      for addr in &addrs {
+        let stream = match timeout {
+          Some(timeout) => TcpStream::connect_timeout(addr, timeout),
+          None => TcpStream::connect(addr),
+        };
+
+        match stream {
+          Ok(_) => return Ok(addr),
+          Err(e) => {
+            let kind = io_error_kind(&e).unwrap_or(ErrorKind::Connect);
+            last_err = CheckError::new(kind, format!("{} on {}", e.kind(), addr));
+          }
+        }
+      }
+
+      Err(last_err)
+    })?;
+    self.detail(addr.to_string());
+
+    Ok(())
+  }
+}